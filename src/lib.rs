@@ -3,6 +3,8 @@ use pyo3::prelude::*;
 mod radix;
 mod node;
 mod prefix;
+mod pyjson;
+mod trie;
 
 use radix::RadixTree;
 use node::RadixNode;
@@ -1,5 +1,7 @@
 use pyo3::prelude::*;
 use pyo3::types::PyDict;
+use std::net::IpAddr;
+use std::str::FromStr;
 use crate::prefix::Prefix;
 
 #[pyclass]
@@ -50,7 +52,20 @@ impl RadixNode {
     fn packed(&self) -> Vec<u8> {
         self.prefix.packed()
     }
-    
+
+    /// The network address as a top-aligned `u128` (the routecore `Bits`
+    /// model `Prefix::bits()` indexes the trie on).
+    #[getter]
+    fn network_int(&self) -> u128 {
+        self.prefix.network_int()
+    }
+
+    /// The last address covered by this prefix, as a top-aligned `u128`.
+    #[getter]
+    fn broadcast_int(&self) -> u128 {
+        self.prefix.broadcast_int()
+    }
+
     #[getter]
     fn data(&self, py: Python) -> PyResult<PyObject> {
         Ok(self.data.clone_ref(py).into())
@@ -78,6 +93,85 @@ impl RadixNode {
             None => Ok(py.None()),
         }
     }
+
+    /// True if `other` (a `RadixNode`, a prefix string, or an address string)
+    /// is contained within this node's prefix.
+    fn __contains__(&self, py: Python, other: PyObject) -> PyResult<bool> {
+        let bound = other.bind(py);
+        if let Ok(node) = bound.extract::<PyRef<RadixNode>>() {
+            return Ok(self.prefix.contains_prefix(&node.prefix));
+        }
+        if let Ok(s) = bound.extract::<String>() {
+            return if s.contains('/') {
+                Ok(self.prefix.contains_prefix(&Prefix::from_str(&s)?))
+            } else {
+                let addr = IpAddr::from_str(&s)
+                    .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("Invalid IP address: {}", e)))?;
+                Ok(self.prefix.contains(&addr))
+            };
+        }
+        Err(pyo3::exceptions::PyValueError::new_err(
+            "argument must be a RadixNode, a prefix string, or an address string",
+        ))
+    }
+
+    fn __eq__(&self, other: PyRef<RadixNode>) -> bool {
+        self.prefix.family() == other.prefix.family()
+            && self.prefix.prefix_len == other.prefix.prefix_len
+            && self.prefix.network_int() == other.prefix.network_int()
+    }
+
+    /// Derived from the same `(family, network_int(), prefix_len)` tuple as
+    /// `__eq__`, so equal nodes always hash equal.
+    fn __hash__(&self) -> u64 {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        (self.prefix.family(), self.prefix.network_int(), self.prefix.prefix_len).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Order by `(family, network_int(), prefix_len)`, i.e. IPv4 before
+    /// IPv6, then by address, then least to most specific.
+    fn __lt__(&self, other: PyRef<RadixNode>) -> bool {
+        (self.prefix.family(), self.prefix.network_int(), self.prefix.prefix_len)
+            < (other.prefix.family(), other.prefix.network_int(), other.prefix.prefix_len)
+    }
+
+    /// The covering prefix `n` levels up, as a fresh node with empty data.
+    fn supernet(&self, py: Python, n: u8) -> PyResult<RadixNode> {
+        Ok(RadixNode::new_with_prefix(py, self.prefix.supernet(n)?))
+    }
+
+    /// All prefixes contained `n` levels down, as fresh nodes with empty data.
+    fn subnet(&self, py: Python, n: u8) -> PyResult<Vec<RadixNode>> {
+        Ok(self
+            .prefix
+            .subnets(n)?
+            .into_iter()
+            .map(|p| RadixNode::new_with_prefix(py, p))
+            .collect())
+    }
+
+    /// Serialize this node as a JSON object `{"prefix": "...", "data": {...}}`.
+    fn to_json(&self, py: Python) -> PyResult<String> {
+        let data = crate::pyjson::to_json_value(self.data.bind(py).as_any())?;
+        let entry = serde_json::json!({
+            "prefix": self.prefix,
+            "data": data,
+        });
+        serde_json::to_string(&entry)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("failed to serialize node: {}", e)))
+    }
+
+    /// Reconstruct a node from JSON produced by `to_json`.
+    #[staticmethod]
+    fn from_json(py: Python, json_str: &str) -> PyResult<RadixNode> {
+        let value: serde_json::Value = serde_json::from_str(json_str)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("invalid JSON: {}", e)))?;
+        RadixNode::from_json_value(py, &value)
+    }
 }
 
 impl RadixNode {
@@ -108,5 +202,31 @@ impl RadixNode {
             parent: None,
         }
     }
-    
+
+    /// Shared by `RadixNode::from_json` and `RadixTree::from_json` to build a
+    /// node from one `{"prefix": ..., "data": ...}` JSON object.
+    pub fn from_json_value(py: Python, value: &serde_json::Value) -> PyResult<RadixNode> {
+        use pyo3::exceptions::PyValueError;
+        use pyo3::types::PyDict;
+
+        let obj = value
+            .as_object()
+            .ok_or_else(|| PyValueError::new_err("expected a JSON object"))?;
+        let prefix_str = obj
+            .get("prefix")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| PyValueError::new_err("missing \"prefix\" field"))?;
+        let prefix = Prefix::from_str(prefix_str)?;
+
+        let empty = serde_json::Value::Object(serde_json::Map::new());
+        let data_value = obj.get("data").unwrap_or(&empty);
+        let data_obj = crate::pyjson::from_json_value(py, data_value)?;
+        let data = data_obj.downcast_bound::<PyDict>(py)?.clone();
+
+        Ok(RadixNode {
+            prefix,
+            data: data.into(),
+            parent: None,
+        })
+    }
 }
\ No newline at end of file
@@ -2,6 +2,7 @@ use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::FromStr;
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Prefix {
@@ -141,25 +142,477 @@ impl Prefix {
     
     /// Get the network address with host bits cleared
     pub fn network_addr(&self) -> IpAddr {
+        self.masked_at(self.prefix_len)
+    }
+
+    /// The address as a `u128`, top-aligned: IPv4 occupies the top 32 bits
+    /// and IPv6 all 128, so bit `N` is counted from the MSB for both
+    /// families (the routecore `Bits(u128)` layout). This is the key
+    /// representation the trie indexes on.
+    pub fn bits(&self) -> u128 {
+        match self.addr {
+            IpAddr::V4(_) => addr_to_bits(self.addr) << 96,
+            IpAddr::V6(_) => addr_to_bits(self.addr),
+        }
+    }
+
+    /// 32 for IPv4, 128 for IPv6.
+    pub fn bit_width(&self) -> u32 {
+        match self.addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    /// `bits()` masked down to `prefix_len`: the network address as a
+    /// top-aligned integer.
+    pub fn network_int(&self) -> u128 {
+        if self.prefix_len == 0 {
+            0
+        } else {
+            self.bits() & (!0u128 << (128 - self.prefix_len as u32))
+        }
+    }
+
+    /// The last address covered by this prefix, as a top-aligned integer
+    /// (i.e. `network_int()` with every host bit set to 1).
+    pub fn broadcast_int(&self) -> u128 {
+        let host_bits = self.bit_width() - self.prefix_len as u32;
+        if host_bits == 0 {
+            return self.network_int();
+        }
+        let host_mask = if host_bits == 128 {
+            !0u128
+        } else {
+            ((1u128 << host_bits) - 1) << (128 - self.bit_width())
+        };
+        self.network_int() | host_mask
+    }
+
+    /// The covering prefix `n` levels up (`prefix_len - n`), with host bits
+    /// below the new length cleared. Errors if `n` underflows past `/0`.
+    pub fn supernet(&self, n: u8) -> PyResult<Prefix> {
+        let new_len = self
+            .prefix_len
+            .checked_sub(n)
+            .ok_or_else(|| PyValueError::new_err("supernet length underflows below /0"))?;
+        Ok(Prefix {
+            addr: self.masked_at(new_len),
+            prefix_len: new_len,
+        })
+    }
+
+    /// `subnets(n)` refuses to materialize more than this many prefixes, so
+    /// that a large `n` fails fast with a `PyValueError` instead of hanging
+    /// the process or exhausting memory.
+    const MAX_SUBNETS: u32 = 1 << 20;
+
+    /// All `2^n` prefixes contained `n` levels down (`prefix_len + n`).
+    /// Errors if the new length would exceed the address width, or if `n`
+    /// is large enough that `2^n` would overflow a `u128` shift or produce
+    /// an unreasonably large `Vec`.
+    pub fn subnets(&self, n: u8) -> PyResult<Vec<Prefix>> {
+        let bit_width = self.bit_width();
+        let new_len = self.prefix_len.checked_add(n).filter(|&l| (l as u32) <= bit_width);
+        let new_len = new_len.ok_or_else(|| {
+            PyValueError::new_err("subnet length exceeds the address width")
+        })?;
+        if n as u32 > Self::MAX_SUBNETS.trailing_zeros() {
+            return Err(PyValueError::new_err(format!(
+                "subnet(n) would produce 2^{} prefixes, more than the limit of {}",
+                n,
+                Self::MAX_SUBNETS
+            )));
+        }
+
+        let shift = bit_width - new_len as u32;
+        let base = addr_to_bits(self.network_addr());
+        Ok((0..(1u128 << n))
+            .map(|i| Prefix {
+                addr: bits_to_addr(base | (i << shift), self.addr),
+                prefix_len: new_len,
+            })
+            .collect())
+    }
+
+    /// Decompose an inclusive address range `[start, end]` into the minimal
+    /// list of aligned CIDR prefixes that together cover exactly that range.
+    ///
+    /// At each step, the largest block anchored at the current `start` is
+    /// emitted: its mask length is bounded both by `start`'s alignment
+    /// (trailing zero bits) and by how much of the remaining range is left
+    /// (`floor_log2` of the remaining address count), whichever constraint
+    /// is tighter (i.e. whichever requires the longer prefix).
+    pub fn from_range(start: IpAddr, end: IpAddr) -> PyResult<Vec<Prefix>> {
+        match (start, end) {
+            (IpAddr::V4(s), IpAddr::V4(e)) => {
+                let blocks = Self::range_to_blocks(u128::from(u32::from(s)), u128::from(u32::from(e)), 32)?;
+                Ok(blocks
+                    .into_iter()
+                    .map(|(bits, len)| Prefix {
+                        addr: IpAddr::V4(Ipv4Addr::from(bits as u32)),
+                        prefix_len: len,
+                    })
+                    .collect())
+            }
+            (IpAddr::V6(s), IpAddr::V6(e)) => {
+                let blocks = Self::range_to_blocks(u128::from(s), u128::from(e), 128)?;
+                Ok(blocks
+                    .into_iter()
+                    .map(|(bits, len)| Prefix {
+                        addr: IpAddr::V6(Ipv6Addr::from(bits)),
+                        prefix_len: len,
+                    })
+                    .collect())
+            }
+            _ => Err(PyValueError::new_err(
+                "start and end must be the same address family",
+            )),
+        }
+    }
+
+    fn range_to_blocks(start: u128, end: u128, bit_width: u32) -> PyResult<Vec<(u128, u8)>> {
+        if start > end {
+            return Err(PyValueError::new_err(
+                "range start must not be greater than end",
+            ));
+        }
+
+        let mut blocks = Vec::new();
+        let mut lo = start;
+        loop {
+            let trailing_zeros = if lo == 0 {
+                bit_width
+            } else {
+                lo.trailing_zeros().min(bit_width)
+            };
+            let remaining = end - lo;
+            let log2_remaining_plus_one = if remaining == u128::MAX {
+                bit_width
+            } else {
+                (127 - (remaining + 1).leading_zeros()).min(bit_width)
+            };
+            let prefix_len = bit_width
+                .saturating_sub(trailing_zeros)
+                .max(bit_width.saturating_sub(log2_remaining_plus_one));
+
+            blocks.push((lo, prefix_len as u8));
+            if prefix_len == 0 {
+                break;
+            }
+
+            let block_size = 1u128 << (bit_width - prefix_len);
+            match lo.checked_add(block_size) {
+                Some(next) if next <= end => lo = next,
+                _ => break,
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    /// The inclusive first and last address covered by this prefix.
+    pub fn to_range(&self) -> (IpAddr, IpAddr) {
+        let first = self.network_addr();
+        let last = match first {
+            IpAddr::V4(v4) => {
+                let host_bits = 32 - self.prefix_len as u32;
+                let mask = if host_bits == 32 { u32::MAX } else { (1u32 << host_bits) - 1 };
+                IpAddr::V4(Ipv4Addr::from(u32::from(v4) | mask))
+            }
+            IpAddr::V6(v6) => {
+                let host_bits = 128 - self.prefix_len as u32;
+                let mask = if host_bits == 128 { u128::MAX } else { (1u128 << host_bits) - 1 };
+                IpAddr::V6(Ipv6Addr::from(u128::from(v6) | mask))
+            }
+        };
+        (first, last)
+    }
+
+    /// The minimal set of prefixes covering everything in `self` but not in
+    /// `excluded` — the building block for carving holes out of an address
+    /// block (e.g. RPKI resource-set subtraction).
+    ///
+    /// If `excluded` isn't contained in `self`, the result is `[self]`
+    /// unchanged; if they're equal, the result is empty. Otherwise this
+    /// walks from `self.prefix_len` down to `excluded.prefix_len`, splitting
+    /// the current block in two at each step and keeping the half that
+    /// doesn't contain `excluded`, recursing into the half that does. Mixed
+    /// families are treated as non-containing and return `[self]`.
+    pub fn subtract(&self, excluded: &Prefix) -> Vec<Prefix> {
+        let bit_width: u32 = match (self.addr, excluded.addr) {
+            (IpAddr::V4(_), IpAddr::V4(_)) => 32,
+            (IpAddr::V6(_), IpAddr::V6(_)) => 128,
+            _ => return vec![self.clone()],
+        };
+
+        if !self.contains_prefix(excluded) {
+            return vec![self.clone()];
+        }
+        if self.prefix_len == excluded.prefix_len {
+            return Vec::new();
+        }
+
+        let excluded_bits = addr_to_bits(excluded.addr);
+        let mut current_bits = addr_to_bits(self.network_addr());
+        let mut current_len = self.prefix_len;
+        let mut kept = Vec::new();
+
+        while current_len < excluded.prefix_len {
+            let child_len = current_len + 1;
+            let bit_pos = bit_width - child_len as u32;
+            let low_bits = current_bits;
+            let high_bits = current_bits | (1u128 << bit_pos);
+            let excluded_goes_high = (excluded_bits >> bit_pos) & 1 == 1;
+
+            let (keep_bits, descend_bits) = if excluded_goes_high {
+                (low_bits, high_bits)
+            } else {
+                (high_bits, low_bits)
+            };
+
+            kept.push(Prefix {
+                addr: bits_to_addr(keep_bits, self.addr),
+                prefix_len: child_len,
+            });
+            current_bits = descend_bits;
+            current_len = child_len;
+        }
+
+        kept
+    }
+
+    /// Mask the address down to `len` leading bits, regardless of `self.prefix_len`.
+    pub fn masked_at(&self, len: u8) -> IpAddr {
         match self.addr {
             IpAddr::V4(v4) => {
-                if self.prefix_len == 0 {
+                if len == 0 {
                     IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0))
                 } else {
                     let bits = u32::from(v4);
-                    let mask = (!0u32) << (32 - self.prefix_len);
+                    let mask = (!0u32) << (32 - len);
                     IpAddr::V4(Ipv4Addr::from(bits & mask))
                 }
             }
             IpAddr::V6(v6) => {
-                if self.prefix_len == 0 {
+                if len == 0 {
                     IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0))
                 } else {
                     let bits = u128::from(v6);
-                    let mask = (!0u128) << (128 - self.prefix_len);
+                    let mask = (!0u128) << (128 - len);
                     IpAddr::V6(Ipv6Addr::from(bits & mask))
                 }
             }
         }
     }
+}
+
+// Serialize/deserialize through the canonical "addr/len" string form, the
+// same way rust-url serializes a `Url` as a plain string, rather than
+// exposing the struct's internal fields. Unlike rust-url we don't gate this
+// behind an optional `serde` feature: this crate is a pyo3 extension module
+// with no external Rust consumers whose dependency tree we'd be protecting,
+// so serde is already a plain dependency and a feature flag here would just
+// be ceremony.
+impl Serialize for Prefix {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.prefix())
+    }
+}
+
+impl<'de> Deserialize<'de> for Prefix {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Prefix::from_str(&s).map_err(|e| serde::de::Error::custom(e.to_string()))
+    }
+}
+
+fn addr_to_bits(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(v4) => u128::from(u32::from(v4)),
+        IpAddr::V6(v6) => u128::from(v6),
+    }
+}
+
+/// Rebuild an `IpAddr` from a bit pattern, taking the family from `like`.
+fn bits_to_addr(bits: u128, like: IpAddr) -> IpAddr {
+    match like {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(bits as u32)),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(bits)),
+    }
+}
+
+/// Collapse a set of prefixes into the minimal equivalent covering set.
+///
+/// IPv4 and IPv6 prefixes are aggregated independently. A prefix already
+/// covered by a shorter-or-equal prefix in the input is dropped, then
+/// sibling pairs that together fill their parent block are merged
+/// repeatedly until no more merges are possible (a merge at length `L` can
+/// expose a new mergeable sibling at `L-1`).
+pub fn aggregate(prefixes: &[Prefix]) -> Vec<Prefix> {
+    let (v4, v6): (Vec<Prefix>, Vec<Prefix>) =
+        prefixes.iter().cloned().partition(|p| p.addr.is_ipv4());
+
+    let mut result = aggregate_family(v4);
+    result.extend(aggregate_family(v6));
+    result
+}
+
+fn aggregate_family(mut prefixes: Vec<Prefix>) -> Vec<Prefix> {
+    if prefixes.is_empty() {
+        return prefixes;
+    }
+
+    prefixes.sort_by(|a, b| a.bits().cmp(&b.bits()).then(a.prefix_len.cmp(&b.prefix_len)));
+
+    // Drop any prefix already contained in an earlier, shorter-or-equal one.
+    let mut reduced: Vec<Prefix> = Vec::new();
+    for p in prefixes {
+        let already_covered = reduced
+            .iter()
+            .any(|kept| kept.prefix_len <= p.prefix_len && kept.contains_prefix(&p));
+        if !already_covered {
+            reduced.push(p);
+        }
+    }
+
+    // Repeatedly merge sibling pairs until a fixed point.
+    loop {
+        reduced.sort_by(|a, b| a.bits().cmp(&b.bits()).then(a.prefix_len.cmp(&b.prefix_len)));
+
+        let mut merged = Vec::with_capacity(reduced.len());
+        let mut changed = false;
+        let mut i = 0;
+        while i < reduced.len() {
+            if i + 1 < reduced.len() {
+                if let Some(parent) = merge_siblings(&reduced[i], &reduced[i + 1]) {
+                    merged.push(parent);
+                    changed = true;
+                    i += 2;
+                    continue;
+                }
+            }
+            merged.push(reduced[i].clone());
+            i += 1;
+        }
+
+        reduced = merged;
+        if !changed {
+            break;
+        }
+    }
+
+    reduced
+}
+
+/// If `a` and `b` are the two halves of the same `prefix_len - 1` supernet
+/// (equal length, sharing a parent, differing only in the last bit), return
+/// that supernet. Otherwise `None`.
+fn merge_siblings(a: &Prefix, b: &Prefix) -> Option<Prefix> {
+    if a.prefix_len == 0 || a.prefix_len != b.prefix_len {
+        return None;
+    }
+    let parent_len = a.prefix_len - 1;
+    if a.masked_at(parent_len) != b.masked_at(parent_len) {
+        return None;
+    }
+    if a.network_addr() == b.network_addr() {
+        return None;
+    }
+    Some(Prefix {
+        addr: a.masked_at(parent_len),
+        prefix_len: parent_len,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(s: &str) -> Prefix {
+        Prefix::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn to_range_round_trips_through_from_range() {
+        let prefix = p("10.1.2.0/24");
+        let (start, end) = prefix.to_range();
+        assert_eq!(start, IpAddr::from_str("10.1.2.0").unwrap());
+        assert_eq!(end, IpAddr::from_str("10.1.2.255").unwrap());
+        assert_eq!(Prefix::from_range(start, end).unwrap(), vec![prefix]);
+    }
+
+    #[test]
+    fn from_range_splits_a_non_aligned_range_into_minimal_blocks() {
+        let start = IpAddr::from_str("192.168.0.0").unwrap();
+        let end = IpAddr::from_str("192.168.0.2").unwrap();
+        let blocks = Prefix::from_range(start, end).unwrap();
+        assert_eq!(
+            blocks,
+            vec![p("192.168.0.0/31"), p("192.168.0.2/32")]
+        );
+    }
+
+    #[test]
+    fn from_range_rejects_mismatched_families() {
+        let start = IpAddr::from_str("10.0.0.0").unwrap();
+        let end = IpAddr::from_str("::1").unwrap();
+        assert!(Prefix::from_range(start, end).is_err());
+    }
+
+    #[test]
+    fn from_range_rejects_start_after_end() {
+        let start = IpAddr::from_str("10.0.0.2").unwrap();
+        let end = IpAddr::from_str("10.0.0.0").unwrap();
+        assert!(Prefix::from_range(start, end).is_err());
+    }
+
+    #[test]
+    fn subtract_splits_around_the_excluded_block() {
+        let result = p("10.0.0.0/24").subtract(&p("10.0.0.128/25"));
+        assert_eq!(result, vec![p("10.0.0.0/25")]);
+    }
+
+    #[test]
+    fn subtract_of_a_non_contained_prefix_is_a_no_op() {
+        let result = p("10.0.0.0/24").subtract(&p("192.168.0.0/25"));
+        assert_eq!(result, vec![p("10.0.0.0/24")]);
+    }
+
+    #[test]
+    fn subtract_of_self_is_empty() {
+        assert!(p("10.0.0.0/24").subtract(&p("10.0.0.0/24")).is_empty());
+    }
+
+    #[test]
+    fn aggregate_family_merges_sibling_pair() {
+        let result = aggregate_family(vec![p("10.0.0.0/25"), p("10.0.0.128/25")]);
+        assert_eq!(result, vec![p("10.0.0.0/24")]);
+    }
+
+    #[test]
+    fn aggregate_family_drops_prefixes_already_covered() {
+        let result = aggregate_family(vec![p("10.0.0.0/24"), p("10.0.0.0/25")]);
+        assert_eq!(result, vec![p("10.0.0.0/24")]);
+    }
+
+    #[test]
+    fn aggregate_family_leaves_unmergeable_prefixes_alone() {
+        let mut result = aggregate_family(vec![p("10.0.0.0/25"), p("10.0.1.0/25")]);
+        result.sort_by_key(|pfx| pfx.bits());
+        assert_eq!(result, vec![p("10.0.0.0/25"), p("10.0.1.0/25")]);
+    }
+
+    #[test]
+    fn subnets_rejects_n_above_the_cap() {
+        assert!(p("::/0").subnets(Prefix::MAX_SUBNETS.trailing_zeros() as u8 + 1).is_err());
+    }
+
+    #[test]
+    fn subnets_at_the_address_width_boundary_does_not_panic() {
+        // Regression test: n == bit_width - prefix_len used to overflow the
+        // `1u128 << n` shift for an all-128 n; the cap now rejects it before
+        // that arithmetic runs.
+        assert!(p("::/0").subnets(128).is_err());
+    }
 }
\ No newline at end of file
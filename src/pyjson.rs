@@ -0,0 +1,72 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList};
+
+/// Convert a Python object into a `serde_json::Value`, supporting the
+/// JSON-representable subset: `None`, `bool`, `int`, `float`, `str`, and
+/// lists/dicts of those (dict keys must be strings).
+pub fn to_json_value(obj: &Bound<PyAny>) -> PyResult<serde_json::Value> {
+    if obj.is_none() {
+        return Ok(serde_json::Value::Null);
+    }
+    if let Ok(b) = obj.downcast::<PyBool>() {
+        return Ok(serde_json::Value::Bool(b.is_true()));
+    }
+    if let Ok(i) = obj.extract::<i64>() {
+        return Ok(serde_json::Value::from(i));
+    }
+    if let Ok(f) = obj.extract::<f64>() {
+        return Ok(serde_json::Value::from(f));
+    }
+    if let Ok(s) = obj.extract::<String>() {
+        return Ok(serde_json::Value::String(s));
+    }
+    if let Ok(list) = obj.downcast::<PyList>() {
+        let mut items = Vec::with_capacity(list.len());
+        for item in list.iter() {
+            items.push(to_json_value(&item)?);
+        }
+        return Ok(serde_json::Value::Array(items));
+    }
+    if let Ok(dict) = obj.downcast::<PyDict>() {
+        let mut map = serde_json::Map::with_capacity(dict.len());
+        for (key, value) in dict.iter() {
+            let key: String = key.extract()?;
+            map.insert(key, to_json_value(&value)?);
+        }
+        return Ok(serde_json::Value::Object(map));
+    }
+    Err(PyValueError::new_err(
+        "value is not JSON-encodable (expected None, bool, int, float, str, list, or dict)",
+    ))
+}
+
+/// Convert a `serde_json::Value` back into a Python object.
+pub fn from_json_value(py: Python, value: &serde_json::Value) -> PyResult<PyObject> {
+    match value {
+        serde_json::Value::Null => Ok(py.None()),
+        serde_json::Value::Bool(b) => Ok(b.into_pyobject(py)?.to_owned().into_any().unbind()),
+        serde_json::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Ok(i.into_pyobject(py)?.into_any().unbind())
+            } else {
+                Ok(n.as_f64().unwrap_or(0.0).into_pyobject(py)?.into_any().unbind())
+            }
+        }
+        serde_json::Value::String(s) => Ok(s.into_pyobject(py)?.into_any().unbind()),
+        serde_json::Value::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(from_json_value(py, item)?)?;
+            }
+            Ok(list.into())
+        }
+        serde_json::Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, value) in map {
+                dict.set_item(key, from_json_value(py, value)?)?;
+            }
+            Ok(dict.into())
+        }
+    }
+}
@@ -1,31 +1,230 @@
 use pyo3::prelude::*;
-use pyo3::exceptions::{PyValueError, PyKeyError, PyTypeError};
-use std::collections::HashMap;
+use pyo3::exceptions::{PyValueError, PyKeyError};
+use pyo3::types::PyDict;
 use std::net::IpAddr;
 use std::str::FromStr;
 
 use crate::prefix::Prefix;
 use crate::node::RadixNode;
+use crate::trie::{self, TrieNode, InsertOutcome};
+
+fn read_bytes<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> PyResult<&'a [u8]> {
+    let end = pos
+        .checked_add(len)
+        .ok_or_else(|| PyValueError::new_err("truncated buffer"))?;
+    if end > data.len() {
+        return Err(PyValueError::new_err("truncated buffer"));
+    }
+    let slice = &data[*pos..end];
+    *pos = end;
+    Ok(slice)
+}
+
+fn read_u8(data: &[u8], pos: &mut usize) -> PyResult<u8> {
+    Ok(read_bytes(data, pos, 1)?[0])
+}
+
+fn read_u32(data: &[u8], pos: &mut usize) -> PyResult<u32> {
+    let bytes: [u8; 4] = read_bytes(data, pos, 4)?.try_into().unwrap();
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Parse the `(network, masklen, packed)` argument triple shared by most
+/// lookup/mutation methods into a `Prefix`.
+fn parse_network_masklen_packed(
+    network: Option<String>,
+    masklen: Option<i32>,
+    packed: Option<Vec<u8>>,
+) -> PyResult<Prefix> {
+    match (network, masklen, packed) {
+        (Some(net), None, None) => Prefix::from_str(&net),
+        (Some(net), Some(mask), None) => {
+            if !(0..=255).contains(&mask) {
+                return Err(PyValueError::new_err(format!("Invalid prefix length: {}", mask)));
+            }
+            Prefix::from_network_masklen(&net, mask as u8)
+        }
+        (None, Some(mask), Some(packed_addr)) => {
+            if !(0..=255).contains(&mask) {
+                return Err(PyValueError::new_err(format!("Invalid prefix length: {}", mask)));
+            }
+            Prefix::from_packed(&packed_addr, mask as u8)
+        }
+        _ => Err(PyValueError::new_err(
+            "Must specify either network (with optional masklen) or packed address with masklen",
+        )),
+    }
+}
 
 enum SearchTarget {
     Address(IpAddr),
     Prefix(Prefix),
 }
 
+fn parse_search_target(network: Option<String>, packed: Option<Vec<u8>>) -> PyResult<SearchTarget> {
+    match (network, packed) {
+        (Some(net), None) => {
+            if net.contains('/') {
+                Ok(SearchTarget::Prefix(Prefix::from_str(&net)?))
+            } else {
+                let addr = IpAddr::from_str(&net)
+                    .map_err(|e| PyValueError::new_err(format!("Invalid IP address: {}", e)))?;
+                Ok(SearchTarget::Address(addr))
+            }
+        }
+        (None, Some(packed_addr)) => {
+            let addr = match packed_addr.len() {
+                4 => {
+                    let bytes: [u8; 4] = packed_addr
+                        .try_into()
+                        .map_err(|_| PyValueError::new_err("Invalid IPv4 packed address"))?;
+                    IpAddr::V4(std::net::Ipv4Addr::from(bytes))
+                }
+                16 => {
+                    let bytes: [u8; 16] = packed_addr
+                        .try_into()
+                        .map_err(|_| PyValueError::new_err("Invalid IPv6 packed address"))?;
+                    IpAddr::V6(std::net::Ipv6Addr::from(bytes))
+                }
+                _ => return Err(PyValueError::new_err("Packed address must be 4 or 16 bytes")),
+            };
+            Ok(SearchTarget::Address(addr))
+        }
+        _ => Err(PyValueError::new_err("Must specify either network or packed address")),
+    }
+}
+
+/// Parse one `search_best_batch` entry, accepting either an IP string or
+/// packed 4-/16-byte `bytes`.
+fn parse_address_entry(py: Python, entry: &PyObject) -> PyResult<IpAddr> {
+    let bound = entry.bind(py);
+    if let Ok(s) = bound.extract::<String>() {
+        return IpAddr::from_str(&s)
+            .map_err(|e| PyValueError::new_err(format!("Invalid IP address: {}", e)));
+    }
+    if let Ok(packed) = bound.extract::<Vec<u8>>() {
+        return match packed.len() {
+            4 => {
+                let bytes: [u8; 4] = packed.try_into().unwrap();
+                Ok(IpAddr::V4(std::net::Ipv4Addr::from(bytes)))
+            }
+            16 => {
+                let bytes: [u8; 16] = packed.try_into().unwrap();
+                Ok(IpAddr::V6(std::net::Ipv6Addr::from(bytes)))
+            }
+            _ => Err(PyValueError::new_err("Packed address must be 4 or 16 bytes")),
+        };
+    }
+    Err(PyValueError::new_err(
+        "Each address must be a string or packed bytes",
+    ))
+}
+
+fn addr_bits(addr: &IpAddr) -> (u128, u32) {
+    match addr {
+        IpAddr::V4(v4) => (u128::from(u32::from(*v4)) << 96, 32),
+        IpAddr::V6(v6) => (u128::from(*v6), 128),
+    }
+}
+
+/// An address as a plain (non-top-aligned) integer, for range arithmetic
+/// where IPv4 and IPv6 are never compared against each other.
+fn raw_bits(addr: &IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(v4) => u128::from(u32::from(*v4)),
+        IpAddr::V6(v6) => u128::from(*v6),
+    }
+}
+
+fn raw_addr(bits: u128, is_v6: bool) -> IpAddr {
+    if is_v6 {
+        IpAddr::V6(std::net::Ipv6Addr::from(bits))
+    } else {
+        IpAddr::V4(std::net::Ipv4Addr::from(bits as u32))
+    }
+}
+
+/// Merge a family's prefixes (already converted to inclusive ranges via
+/// `Prefix::to_range`) into the minimal set of `(start, end)` spans,
+/// combining any that are contiguous or overlapping once sorted.
+fn coalesce_ranges(prefixes: &[Prefix]) -> Vec<(IpAddr, IpAddr)> {
+    if prefixes.is_empty() {
+        return Vec::new();
+    }
+    let is_v6 = prefixes[0].addr.is_ipv6();
+
+    let mut spans: Vec<(u128, u128)> = prefixes
+        .iter()
+        .map(|p| {
+            let (start, end) = p.to_range();
+            (raw_bits(&start), raw_bits(&end))
+        })
+        .collect();
+    spans.sort();
+
+    let mut merged: Vec<(u128, u128)> = Vec::new();
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => {
+                if end > last.1 {
+                    last.1 = end;
+                }
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(s, e)| (raw_addr(s, is_v6), raw_addr(e, is_v6)))
+        .collect()
+}
+
 #[pyclass]
 pub struct RadixTree {
-    py_nodes: HashMap<String, Py<RadixNode>>,
+    v4: Option<Box<TrieNode>>,
+    v6: Option<Box<TrieNode>>,
+}
+
+impl RadixTree {
+    fn root_for(&self, is_v6: bool) -> &Option<Box<TrieNode>> {
+        if is_v6 { &self.v6 } else { &self.v4 }
+    }
+
+    fn root_for_mut(&mut self, is_v6: bool) -> &mut Option<Box<TrieNode>> {
+        if is_v6 { &mut self.v6 } else { &mut self.v4 }
+    }
+
+    fn all_nodes(&self, py: Python) -> Vec<Py<RadixNode>> {
+        let mut out = Vec::new();
+        trie::collect_all(&self.v4, py, &mut out);
+        trie::collect_all(&self.v6, py, &mut out);
+        out
+    }
+
+    fn matches_for_target(&self, target: &SearchTarget, py: Python) -> Vec<Py<RadixNode>> {
+        match target {
+            SearchTarget::Address(addr) => {
+                let (bits, bit_width) = addr_bits(addr);
+                trie::search_along_path(self.root_for(bit_width == 128), bits, bit_width as u8, py)
+            }
+            SearchTarget::Prefix(prefix) => trie::search_along_path(
+                self.root_for(prefix.bit_width() == 128),
+                prefix.bits(),
+                prefix.prefix_len,
+                py,
+            ),
+        }
+    }
 }
 
 #[pymethods]
 impl RadixTree {
     #[new]
     fn new() -> Self {
-        RadixTree {
-            py_nodes: HashMap::new(),
-        }
+        RadixTree { v4: None, v6: None }
     }
-    
+
     #[pyo3(signature = (network = None, masklen = None, packed = None))]
     fn add(
         &mut self,
@@ -34,52 +233,23 @@ impl RadixTree {
         masklen: Option<i32>,
         packed: Option<Vec<u8>>,
     ) -> PyResult<PyObject> {
-        let prefix = match (network, masklen, packed) {
-            (Some(net), None, None) => {
-                // CIDR format like "10.0.0.0/8"
-                Prefix::from_str(&net)?
-            }
-            (Some(net), Some(mask), None) => {
-                // Separate network and masklen - validate range first
-                if mask < 0 || mask > 255 {
-                    return Err(PyValueError::new_err(
-                        format!("Invalid prefix length: {}", mask)
-                    ));
-                }
-                Prefix::from_network_masklen(&net, mask as u8)?
-            }
-            (None, Some(mask), Some(packed_addr)) => {
-                // Packed address format - validate range first
-                if mask < 0 || mask > 255 {
-                    return Err(PyValueError::new_err(
-                        format!("Invalid prefix length: {}", mask)
-                    ));
-                }
-                Prefix::from_packed(&packed_addr, mask as u8)?
-            }
-            _ => {
-                return Err(PyTypeError::new_err(
-                    "Must specify either network (with optional masklen) or packed address with masklen"
-                ));
-            }
+        let prefix = parse_network_masklen_packed(network, masklen, packed)?;
+        let normalized = Prefix::new(prefix.network_addr(), prefix.prefix_len)?;
+        let bits = normalized.bits();
+        let len = normalized.prefix_len;
+        let bit_width = normalized.bit_width();
+        let root = self.root_for_mut(bit_width == 128);
+
+        let outcome = trie::insert(root, bits, len, py, || {
+            Py::new(py, RadixNode::new_with_prefix(py, normalized.clone()))
+        })?;
+
+        let node = match outcome {
+            InsertOutcome::Existing(node) | InsertOutcome::Inserted(node) => node,
         };
-        
-        let normalized_prefix = Prefix::new(prefix.network_addr(), prefix.prefix_len)?;
-        let key = normalized_prefix.prefix();
-        
-        if let Some(existing_py_node) = self.py_nodes.get(&key) {
-            Ok(existing_py_node.clone_ref(py).into_any())
-        } else {
-            let node = RadixNode::new_with_prefix(py, normalized_prefix);
-            let py_node = Py::new(py, node)?;
-            
-            // Store the Python object
-            self.py_nodes.insert(key, py_node.clone_ref(py));
-            
-            Ok(py_node.into_any())
-        }
+        Ok(node.into_any())
     }
-    
+
     #[pyo3(signature = (network = None, masklen = None, packed = None))]
     fn delete(
         &mut self,
@@ -87,45 +257,18 @@ impl RadixTree {
         masklen: Option<i32>,
         packed: Option<Vec<u8>>,
     ) -> PyResult<()> {
-        let prefix = match (network, masklen, packed) {
-            (Some(net), None, None) => {
-                Prefix::from_str(&net)?
-            }
-            (Some(net), Some(mask), None) => {
-                // Validate range first
-                if mask < 0 || mask > 255 {
-                    return Err(PyValueError::new_err(
-                        format!("Invalid prefix length: {}", mask)
-                    ));
-                }
-                Prefix::from_network_masklen(&net, mask as u8)?
-            }
-            (None, Some(mask), Some(packed_addr)) => {
-                // Validate range first
-                if mask < 0 || mask > 255 {
-                    return Err(PyValueError::new_err(
-                        format!("Invalid prefix length: {}", mask)
-                    ));
-                }
-                Prefix::from_packed(&packed_addr, mask as u8)?
-            }
-            _ => {
-                return Err(PyValueError::new_err(
-                    "Must specify either network (with optional masklen) or packed address with masklen"
-                ));
-            }
-        };
-        
-        let normalized_prefix = Prefix::new(prefix.network_addr(), prefix.prefix_len)?;
-        let key = normalized_prefix.prefix();
-        
-        if self.py_nodes.remove(&key).is_some() {
+        let prefix = parse_network_masklen_packed(network, masklen, packed)?;
+        let normalized = Prefix::new(prefix.network_addr(), prefix.prefix_len)?;
+        let bit_width = normalized.bit_width();
+        let root = self.root_for_mut(bit_width == 128);
+
+        if trie::delete(root, normalized.bits(), normalized.prefix_len) {
             Ok(())
         } else {
             Err(PyKeyError::new_err("match not found"))
         }
     }
-    
+
     #[pyo3(signature = (network = None, masklen = None, packed = None))]
     fn search_exact(
         &self,
@@ -134,41 +277,14 @@ impl RadixTree {
         masklen: Option<i32>,
         packed: Option<Vec<u8>>,
     ) -> PyResult<Option<PyObject>> {
-        let prefix = match (network, masklen, packed) {
-            (Some(net), None, None) => {
-                Prefix::from_str(&net)?
-            }
-            (Some(net), Some(mask), None) => {
-                // Validate range first
-                if mask < 0 || mask > 255 {
-                    return Err(PyValueError::new_err(
-                        format!("Invalid prefix length: {}", mask)
-                    ));
-                }
-                Prefix::from_network_masklen(&net, mask as u8)?
-            }
-            (None, Some(mask), Some(packed_addr)) => {
-                // Validate range first
-                if mask < 0 || mask > 255 {
-                    return Err(PyValueError::new_err(
-                        format!("Invalid prefix length: {}", mask)
-                    ));
-                }
-                Prefix::from_packed(&packed_addr, mask as u8)?
-            }
-            _ => {
-                return Err(PyValueError::new_err(
-                    "Must specify either network (with optional masklen) or packed address with masklen"
-                ));
-            }
-        };
-        
-        let normalized_prefix = Prefix::new(prefix.network_addr(), prefix.prefix_len)?;
-        let key = normalized_prefix.prefix();
-        
-        Ok(self.py_nodes.get(&key).map(|py_node| py_node.clone_ref(py).into_any()))
+        let prefix = parse_network_masklen_packed(network, masklen, packed)?;
+        let normalized = Prefix::new(prefix.network_addr(), prefix.prefix_len)?;
+        let bit_width = normalized.bit_width();
+        let root = self.root_for(bit_width == 128);
+
+        Ok(trie::search_exact(root, normalized.bits(), normalized.prefix_len, py).map(|n| n.into_any()))
     }
-    
+
     #[pyo3(signature = (network = None, packed = None))]
     fn search_best(
         &self,
@@ -176,68 +292,27 @@ impl RadixTree {
         network: Option<String>,
         packed: Option<Vec<u8>>,
     ) -> PyResult<Option<PyObject>> {
-        let search_target = match (network, packed) {
-            (Some(net), None) => {
-                // Try to parse as CIDR first, then as IP address
-                if net.contains('/') {
-                    // For CIDR notation, we need to find prefixes that contain the entire range
-                    let search_prefix = Prefix::from_str(&net)?;
-                    SearchTarget::Prefix(search_prefix)
-                } else {
-                    // For IP address, find prefixes that contain this address
-                    let addr = IpAddr::from_str(&net)
-                        .map_err(|e| PyValueError::new_err(format!("Invalid IP address: {}", e)))?;
-                    SearchTarget::Address(addr)
-                }
-            }
-            (None, Some(packed_addr)) => {
-                let addr = match packed_addr.len() {
-                    4 => {
-                        let bytes: [u8; 4] = packed_addr.try_into()
-                            .map_err(|_| PyValueError::new_err("Invalid IPv4 packed address"))?;
-                        IpAddr::V4(std::net::Ipv4Addr::from(bytes))
-                    }
-                    16 => {
-                        let bytes: [u8; 16] = packed_addr.try_into()
-                            .map_err(|_| PyValueError::new_err("Invalid IPv6 packed address"))?;
-                        IpAddr::V6(std::net::Ipv6Addr::from(bytes))
-                    }
-                    _ => return Err(PyValueError::new_err("Packed address must be 4 or 16 bytes")),
-                };
-                SearchTarget::Address(addr)
-            }
-            _ => {
-                return Err(PyValueError::new_err(
-                    "Must specify either network or packed address"
-                ));
-            }
-        };
-        
-        let mut best_match: Option<&str> = None;
-        let mut best_len = 0;
-        
-        for (key, py_node) in &self.py_nodes {
-            let node_ref = py_node.bind(py).borrow();
-            let matches = match &search_target {
-                SearchTarget::Address(addr) => {
-                    // For address search, find prefixes that contain this address
-                    node_ref.prefix.contains(addr)
-                }
-                SearchTarget::Prefix(search_prefix) => {
-                    // For prefix search, find prefixes that contain the entire search prefix
-                    node_ref.prefix.contains_prefix(search_prefix)
-                }
-            };
-            
-            if matches && node_ref.prefix.prefix_len >= best_len {
-                best_match = Some(key);
-                best_len = node_ref.prefix.prefix_len;
-            }
-        }
-        
-        Ok(best_match.and_then(|key| self.py_nodes.get(key).map(|py_node| py_node.clone_ref(py).into_any())))
+        let target = parse_search_target(network, packed)?;
+        Ok(self.matches_for_target(&target, py).into_iter().last().map(|n| n.into_any()))
+    }
+
+    /// Longest-prefix match for many addresses in one call, so a dataplane
+    /// classifying a batch of packets pays the Python↔Rust argument-parsing
+    /// and borrow overhead once instead of once per address. `addresses` may
+    /// mix IP strings and packed 4-/16-byte `bytes`; results are returned in
+    /// the same order, with `None` for addresses that match nothing.
+    fn search_best_batch(&self, py: Python, addresses: Vec<PyObject>) -> PyResult<Vec<Option<PyObject>>> {
+        addresses
+            .into_iter()
+            .map(|entry| {
+                let addr = parse_address_entry(py, &entry)?;
+                let (bits, bit_width) = addr_bits(&addr);
+                let matches = trie::search_along_path(self.root_for(bit_width == 128), bits, bit_width as u8, py);
+                Ok(matches.into_iter().last().map(|n| n.into_any()))
+            })
+            .collect()
     }
-    
+
     #[pyo3(signature = (network = None, packed = None))]
     fn search_worst(
         &self,
@@ -245,68 +320,10 @@ impl RadixTree {
         network: Option<String>,
         packed: Option<Vec<u8>>,
     ) -> PyResult<Option<PyObject>> {
-        let search_target = match (network, packed) {
-            (Some(net), None) => {
-                // Try to parse as CIDR first, then as IP address
-                if net.contains('/') {
-                    // For CIDR notation, we need to find prefixes that contain the entire range
-                    let search_prefix = Prefix::from_str(&net)?;
-                    SearchTarget::Prefix(search_prefix)
-                } else {
-                    // For IP address, find prefixes that contain this address
-                    let addr = IpAddr::from_str(&net)
-                        .map_err(|e| PyValueError::new_err(format!("Invalid IP address: {}", e)))?;
-                    SearchTarget::Address(addr)
-                }
-            }
-            (None, Some(packed_addr)) => {
-                let addr = match packed_addr.len() {
-                    4 => {
-                        let bytes: [u8; 4] = packed_addr.try_into()
-                            .map_err(|_| PyValueError::new_err("Invalid IPv4 packed address"))?;
-                        IpAddr::V4(std::net::Ipv4Addr::from(bytes))
-                    }
-                    16 => {
-                        let bytes: [u8; 16] = packed_addr.try_into()
-                            .map_err(|_| PyValueError::new_err("Invalid IPv6 packed address"))?;
-                        IpAddr::V6(std::net::Ipv6Addr::from(bytes))
-                    }
-                    _ => return Err(PyValueError::new_err("Packed address must be 4 or 16 bytes")),
-                };
-                SearchTarget::Address(addr)
-            }
-            _ => {
-                return Err(PyValueError::new_err(
-                    "Must specify either network or packed address"
-                ));
-            }
-        };
-        
-        let mut worst_match: Option<&str> = None;
-        let mut worst_len = 255;
-        
-        for (key, py_node) in &self.py_nodes {
-            let node_ref = py_node.bind(py).borrow();
-            let matches = match &search_target {
-                SearchTarget::Address(addr) => {
-                    // For address search, find prefixes that contain this address
-                    node_ref.prefix.contains(addr)
-                }
-                SearchTarget::Prefix(search_prefix) => {
-                    // For prefix search, find prefixes that contain the entire search prefix
-                    node_ref.prefix.contains_prefix(search_prefix)
-                }
-            };
-            
-            if matches && node_ref.prefix.prefix_len <= worst_len {
-                worst_match = Some(key);
-                worst_len = node_ref.prefix.prefix_len;
-            }
-        }
-        
-        Ok(worst_match.and_then(|key| self.py_nodes.get(key).map(|py_node| py_node.clone_ref(py).into_any())))
+        let target = parse_search_target(network, packed)?;
+        Ok(self.matches_for_target(&target, py).into_iter().next().map(|n| n.into_any()))
     }
-    
+
     #[pyo3(signature = (network = None, masklen = None, packed = None))]
     fn search_covered(
         &self,
@@ -315,54 +332,20 @@ impl RadixTree {
         masklen: Option<i32>,
         packed: Option<Vec<u8>>,
     ) -> PyResult<Vec<PyObject>> {
-        let prefix = match (network, masklen, packed) {
-            (Some(net), None, None) => {
-                Prefix::from_str(&net)?
-            }
-            (Some(net), Some(mask), None) => {
-                // Validate range first
-                if mask < 0 || mask > 255 {
-                    return Err(PyValueError::new_err(
-                        format!("Invalid prefix length: {}", mask)
-                    ));
-                }
-                Prefix::from_network_masklen(&net, mask as u8)?
-            }
-            (None, Some(mask), Some(packed_addr)) => {
-                // Validate range first
-                if mask < 0 || mask > 255 {
-                    return Err(PyValueError::new_err(
-                        format!("Invalid prefix length: {}", mask)
-                    ));
-                }
-                Prefix::from_packed(&packed_addr, mask as u8)?
-            }
-            _ => {
-                return Err(PyValueError::new_err(
-                    "Must specify either network (with optional masklen) or packed address with masklen"
-                ));
-            }
-        };
-        
-        let mut covered = Vec::new();
-        
-        for (_key, py_node) in &self.py_nodes {
-            let node_ref = py_node.bind(py).borrow();
-            if prefix.contains_prefix(&node_ref.prefix) {
-                covered.push(py_node.clone_ref(py).into_any());
-            }
-        }
-        
+        let prefix = parse_network_masklen_packed(network, masklen, packed)?;
+        let root = self.root_for(prefix.bit_width() == 128);
+
+        let mut nodes = trie::search_covered(root, prefix.bits(), prefix.prefix_len, py);
         // Sort by prefix length (longest first)
-        covered.sort_by(|a: &PyObject, b: &PyObject| {
-            let a_node = a.extract::<PyRef<RadixNode>>(py).unwrap();
-            let b_node = b.extract::<PyRef<RadixNode>>(py).unwrap();
-            b_node.prefix.prefix_len.cmp(&a_node.prefix.prefix_len)
+        nodes.sort_by(|a, b| {
+            let a_len = a.bind(py).borrow().prefix.prefix_len;
+            let b_len = b.bind(py).borrow().prefix.prefix_len;
+            b_len.cmp(&a_len)
         });
-        
-        Ok(covered)
+
+        Ok(nodes.into_iter().map(|n| n.into_any()).collect())
     }
-    
+
     #[pyo3(signature = (network = None, masklen = None, packed = None))]
     fn search_covering(
         &self,
@@ -371,75 +354,256 @@ impl RadixTree {
         masklen: Option<i32>,
         packed: Option<Vec<u8>>,
     ) -> PyResult<Vec<PyObject>> {
-        let prefix = match (network, masklen, packed) {
-            (Some(net), None, None) => {
-                Prefix::from_str(&net)?
+        let prefix = parse_network_masklen_packed(network, masklen, packed)?;
+        let root = self.root_for(prefix.bit_width() == 128);
+
+        let mut nodes = trie::search_along_path(root, prefix.bits(), prefix.prefix_len, py);
+        // Sort by prefix length (longest first - most specific first)
+        nodes.sort_by(|a, b| {
+            let a_len = a.bind(py).borrow().prefix.prefix_len;
+            let b_len = b.bind(py).borrow().prefix.prefix_len;
+            b_len.cmp(&a_len)
+        });
+
+        Ok(nodes.into_iter().map(|n| n.into_any()).collect())
+    }
+
+    /// Store an inclusive address range as the minimal set of CIDR prefixes
+    /// it decomposes into, inserting each as a node. Returns the list of
+    /// nodes added (or already present).
+    fn add_range(&mut self, py: Python, start: String, end: String) -> PyResult<Vec<PyObject>> {
+        let start_addr = IpAddr::from_str(&start)
+            .map_err(|e| PyValueError::new_err(format!("Invalid IP address: {}", e)))?;
+        let end_addr = IpAddr::from_str(&end)
+            .map_err(|e| PyValueError::new_err(format!("Invalid IP address: {}", e)))?;
+
+        let mut nodes = Vec::new();
+        for prefix in Prefix::from_range(start_addr, end_addr)? {
+            let bits = prefix.bits();
+            let len = prefix.prefix_len;
+            let bit_width = prefix.bit_width();
+            let root = self.root_for_mut(bit_width == 128);
+
+            let outcome = trie::insert(root, bits, len, py, || {
+                Py::new(py, RadixNode::new_with_prefix(py, prefix.clone()))
+            })?;
+            let node = match outcome {
+                InsertOutcome::Existing(node) | InsertOutcome::Inserted(node) => node,
+            };
+            nodes.push(node.into_any());
+        }
+
+        Ok(nodes)
+    }
+
+    /// Coalesce every stored prefix back into the minimal list of inclusive
+    /// `(start, end)` address ranges it covers, merging prefixes that are
+    /// contiguous or overlapping even when they aren't aligned CIDR
+    /// siblings. The inverse of `add_range`.
+    fn to_ranges(&self, py: Python) -> Vec<(String, String)> {
+        let prefixes: Vec<Prefix> = self
+            .all_nodes(py)
+            .into_iter()
+            .map(|n| n.bind(py).borrow().prefix.clone())
+            .collect();
+        let (v4, v6): (Vec<Prefix>, Vec<Prefix>) =
+            prefixes.into_iter().partition(|p| p.addr.is_ipv4());
+
+        coalesce_ranges(&v4)
+            .into_iter()
+            .chain(coalesce_ranges(&v6))
+            .map(|(start, end)| (start.to_string(), end.to_string()))
+            .collect()
+    }
+
+    /// Serialize the entire tree into one compact, length-prefixed binary
+    /// blob: a `u32` node count, followed by each node as `[prefix_len]
+    /// [family][packed address][item count][key/value pairs]`, where each
+    /// key/value pair is length-prefixed UTF-8 (values are JSON-encoded
+    /// scalars). This avoids pickling the Python `data` objects.
+    fn dump_bytes(&self, py: Python) -> PyResult<Vec<u8>> {
+        let json = PyModule::import(py, "json")?;
+        let all_nodes = self.all_nodes(py);
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(all_nodes.len() as u32).to_le_bytes());
+
+        for py_node in &all_nodes {
+            let node_ref = py_node.bind(py).borrow();
+            buf.push(node_ref.prefix.prefix_len);
+            buf.push(match node_ref.prefix.family() {
+                2 => 4,
+                _ => 6,
+            });
+            buf.extend_from_slice(&node_ref.prefix.packed());
+
+            let dict = node_ref.data.bind(py);
+            let mut items = Vec::with_capacity(dict.len());
+            for (key, value) in dict.iter() {
+                let key: String = key.extract()?;
+                let value_json: String = json.call_method1("dumps", (value,))?.extract()?;
+                items.push((key, value_json));
             }
-            (Some(net), Some(mask), None) => {
-                // Validate range first
-                if mask < 0 || mask > 255 {
-                    return Err(PyValueError::new_err(
-                        format!("Invalid prefix length: {}", mask)
-                    ));
-                }
-                Prefix::from_network_masklen(&net, mask as u8)?
+
+            buf.extend_from_slice(&(items.len() as u32).to_le_bytes());
+            for (key, value) in items {
+                buf.extend_from_slice(&(key.len() as u32).to_le_bytes());
+                buf.extend_from_slice(key.as_bytes());
+                buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                buf.extend_from_slice(value.as_bytes());
             }
-            (None, Some(mask), Some(packed_addr)) => {
-                // Validate range first
-                if mask < 0 || mask > 255 {
-                    return Err(PyValueError::new_err(
-                        format!("Invalid prefix length: {}", mask)
-                    ));
+        }
+
+        Ok(buf)
+    }
+
+    /// Rebuild a tree from a blob produced by `dump_bytes`, reinserting each
+    /// node through the normal insertion path. Rejects truncated buffers and
+    /// octet counts that don't match the declared family.
+    fn load_bytes(&mut self, py: Python, data: Vec<u8>) -> PyResult<()> {
+        let json = PyModule::import(py, "json")?;
+        let mut pos = 0usize;
+
+        let node_count = read_u32(&data, &mut pos)?;
+        for _ in 0..node_count {
+            let prefix_len = read_u8(&data, &mut pos)?;
+            let family_byte = read_u8(&data, &mut pos)?;
+            let octet_count = match family_byte {
+                4 => 4,
+                6 => 16,
+                other => {
+                    return Err(PyValueError::new_err(format!(
+                        "unknown family discriminator: {}",
+                        other
+                    )))
                 }
-                Prefix::from_packed(&packed_addr, mask as u8)?
+            };
+            let packed = read_bytes(&data, &mut pos, octet_count)?;
+            let prefix = Prefix::from_packed(packed, prefix_len)?;
+
+            let item_count = read_u32(&data, &mut pos)?;
+            let dict = PyDict::new(py);
+            for _ in 0..item_count {
+                let key_len = read_u32(&data, &mut pos)? as usize;
+                let key = std::str::from_utf8(read_bytes(&data, &mut pos, key_len)?)
+                    .map_err(|e| PyValueError::new_err(format!("invalid UTF-8 key: {}", e)))?;
+                let value_len = read_u32(&data, &mut pos)? as usize;
+                let value_json = std::str::from_utf8(read_bytes(&data, &mut pos, value_len)?)
+                    .map_err(|e| PyValueError::new_err(format!("invalid UTF-8 value: {}", e)))?;
+                let value = json.call_method1("loads", (value_json,))?;
+                dict.set_item(key, value)?;
             }
-            _ => {
-                return Err(PyValueError::new_err(
-                    "Must specify either network (with optional masklen) or packed address with masklen"
-                ));
+
+            let dict_py: Py<PyDict> = dict.into();
+            let bits = prefix.bits();
+            let len = prefix.prefix_len;
+            let bit_width = prefix.bit_width();
+            let root = self.root_for_mut(bit_width == 128);
+
+            let outcome = trie::insert(root, bits, len, py, || {
+                let mut node = RadixNode::new_with_prefix(py, prefix.clone());
+                node.data = dict_py.clone_ref(py);
+                Py::new(py, node)
+            })?;
+            if let InsertOutcome::Existing(existing) = outcome {
+                existing.bind(py).borrow_mut().data = dict_py.clone_ref(py);
             }
-        };
-        
-        let mut covering = Vec::new();
-        
-        for (_key, py_node) in &self.py_nodes {
+        }
+
+        Ok(())
+    }
+
+    /// Serialize the whole tree as a JSON array of `{"prefix": "...", "data":
+    /// {...}}` objects, one per node.
+    fn to_json(&self, py: Python) -> PyResult<String> {
+        let all_nodes = self.all_nodes(py);
+        let mut entries = Vec::with_capacity(all_nodes.len());
+        for py_node in &all_nodes {
             let node_ref = py_node.bind(py).borrow();
-            if node_ref.prefix.contains_prefix(&prefix) {
-                covering.push(py_node.clone_ref(py).into_any());
-            }
+            let data = crate::pyjson::to_json_value(node_ref.data.bind(py).as_any())?;
+            entries.push(serde_json::json!({
+                "prefix": node_ref.prefix,
+                "data": data,
+            }));
         }
-        
-        // Sort by prefix length (longest first - most specific first)
-        covering.sort_by(|a: &PyObject, b: &PyObject| {
-            let a_node = a.extract::<PyRef<RadixNode>>(py).unwrap();
-            let b_node = b.extract::<PyRef<RadixNode>>(py).unwrap();
-            b_node.prefix.prefix_len.cmp(&a_node.prefix.prefix_len)
-        });
-        
-        Ok(covering)
+        serde_json::to_string(&entries)
+            .map_err(|e| PyValueError::new_err(format!("failed to serialize tree: {}", e)))
     }
-    
+
+    /// Rebuild a tree from JSON produced by `to_json`, reinserting each entry.
+    #[staticmethod]
+    fn from_json(py: Python, json_str: &str) -> PyResult<RadixTree> {
+        let entries: Vec<serde_json::Value> = serde_json::from_str(json_str)
+            .map_err(|e| PyValueError::new_err(format!("invalid JSON: {}", e)))?;
+
+        let mut tree = RadixTree::new();
+        for entry in &entries {
+            let node = RadixNode::from_json_value(py, entry)?;
+            let prefix = node.prefix.clone();
+            let bits = prefix.bits();
+            let len = prefix.prefix_len;
+            let bit_width = prefix.bit_width();
+            let root = tree.root_for_mut(bit_width == 128);
+            trie::insert(root, bits, len, py, move || Py::new(py, node))?;
+        }
+
+        Ok(tree)
+    }
+
+    /// Collapse this tree into the minimal equivalent set of covering
+    /// prefixes, returned as a new `RadixTree`. IPv4 and IPv6 prefixes are
+    /// aggregated independently; node `data` is not carried over to the
+    /// merged prefixes since a merged prefix no longer corresponds to a
+    /// single original node.
+    fn aggregate(&self, py: Python) -> PyResult<RadixTree> {
+        let prefixes: Vec<Prefix> = self
+            .all_nodes(py)
+            .into_iter()
+            .map(|n| n.bind(py).borrow().prefix.clone())
+            .collect();
+
+        let mut tree = RadixTree::new();
+        for prefix in crate::prefix::aggregate(&prefixes) {
+            let bits = prefix.bits();
+            let len = prefix.prefix_len;
+            let bit_width = prefix.bit_width();
+            let root = tree.root_for_mut(bit_width == 128);
+            trie::insert(root, bits, len, py, || {
+                Py::new(py, RadixNode::new_with_prefix(py, prefix.clone()))
+            })?;
+        }
+
+        Ok(tree)
+    }
+
     fn nodes(&self, py: Python) -> Vec<PyObject> {
-        self.py_nodes.values().map(|py_node| py_node.clone_ref(py).into_any()).collect()
+        self.all_nodes(py).into_iter().map(|n| n.into_any()).collect()
     }
-    
-    fn prefixes(&self) -> Vec<String> {
-        self.py_nodes.keys().cloned().collect()
+
+    fn prefixes(&self, py: Python) -> Vec<String> {
+        self.all_nodes(py)
+            .into_iter()
+            .map(|n| n.bind(py).borrow().prefix.prefix())
+            .collect()
     }
-    
+
     fn __iter__(&self, py: Python) -> PyResult<RadixIterator> {
         // Sort by prefix to ensure consistent ordering
-        let mut sorted_entries: Vec<_> = self.py_nodes.iter().collect();
-        sorted_entries.sort_by_key(|(prefix, _)| prefix.as_str());
-        
-        let nodes: Vec<PyObject> = sorted_entries.into_iter()
-            .map(|(_, py_node)| py_node.clone_ref(py).into_any())
+        let mut entries: Vec<(String, Py<RadixNode>)> = self
+            .all_nodes(py)
+            .into_iter()
+            .map(|n| {
+                let key = n.bind(py).borrow().prefix.prefix();
+                (key, n)
+            })
             .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let nodes: Vec<PyObject> = entries.into_iter().map(|(_, n)| n.into_any()).collect();
         Ok(RadixIterator { nodes, index: 0 })
     }
-    
+
     fn __len__(&self) -> usize {
-        self.py_nodes.len()
+        trie::len(&self.v4) + trie::len(&self.v6)
     }
 }
 
@@ -454,7 +618,7 @@ impl RadixIterator {
     fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
         slf
     }
-    
+
     fn __next__(mut slf: PyRefMut<Self>, py: Python) -> PyResult<Option<PyObject>> {
         if slf.index < slf.nodes.len() {
             let node = slf.nodes[slf.index].clone_ref(py);
@@ -464,4 +628,108 @@ impl RadixIterator {
             Ok(None)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_then_search_exact_roundtrips_and_delete_removes_it() {
+        Python::with_gil(|py| {
+            let mut tree = RadixTree::new();
+            tree.add(py, Some("10.0.0.0/24".to_string()), None, None).unwrap();
+            assert_eq!(tree.__len__(), 1);
+
+            let found = tree
+                .search_exact(py, Some("10.0.0.0/24".to_string()), None, None)
+                .unwrap();
+            assert!(found.is_some());
+
+            tree.delete(Some("10.0.0.0/24".to_string()), None, None).unwrap();
+            assert_eq!(tree.__len__(), 0);
+            assert!(tree.delete(Some("10.0.0.0/24".to_string()), None, None).is_err());
+        });
+    }
+
+    #[test]
+    fn add_range_then_to_ranges_round_trips() {
+        Python::with_gil(|py| {
+            let mut tree = RadixTree::new();
+            tree.add_range(py, "10.0.0.0".to_string(), "10.0.0.5".to_string())
+                .unwrap();
+            let ranges = tree.to_ranges(py);
+            assert_eq!(ranges, vec![("10.0.0.0".to_string(), "10.0.0.5".to_string())]);
+        });
+    }
+
+    #[test]
+    fn search_best_batch_matches_each_address_independently() {
+        Python::with_gil(|py| {
+            let mut tree = RadixTree::new();
+            tree.add(py, Some("10.0.0.0/24".to_string()), None, None).unwrap();
+            tree.add(py, Some("10.0.0.0/16".to_string()), None, None).unwrap();
+
+            let addresses: Vec<PyObject> = vec![
+                "10.0.0.1".into_pyobject(py).unwrap().into_any().unbind(),
+                "192.168.0.1".into_pyobject(py).unwrap().into_any().unbind(),
+            ];
+            let matches = tree.search_best_batch(py, addresses).unwrap();
+            assert!(matches[0].is_some());
+            assert!(matches[1].is_none());
+
+            let best = matches[0].as_ref().unwrap().bind(py).downcast::<RadixNode>().unwrap().borrow();
+            assert_eq!(best.prefix.prefixlen(), 24);
+        });
+    }
+
+    #[test]
+    fn dump_bytes_then_load_bytes_round_trips_prefixes_and_data() {
+        Python::with_gil(|py| {
+            let mut tree = RadixTree::new();
+            let node = tree.add(py, Some("10.0.0.0/24".to_string()), None, None).unwrap();
+            node.bind(py)
+                .downcast::<RadixNode>()
+                .unwrap()
+                .borrow()
+                .set_data_item(py, "label".to_string(), "gateway".into_pyobject(py).unwrap().into_any().unbind())
+                .unwrap();
+
+            let blob = tree.dump_bytes(py).unwrap();
+
+            let mut reloaded = RadixTree::new();
+            reloaded.load_bytes(py, blob).unwrap();
+            assert_eq!(reloaded.__len__(), 1);
+
+            let reloaded_node = reloaded
+                .search_exact(py, Some("10.0.0.0/24".to_string()), None, None)
+                .unwrap()
+                .unwrap();
+            let label = reloaded_node
+                .bind(py)
+                .downcast::<RadixNode>()
+                .unwrap()
+                .borrow()
+                .get_data_item(py, "label")
+                .unwrap()
+                .unwrap();
+            assert_eq!(label.extract::<String>(py).unwrap(), "gateway");
+        });
+    }
+
+    #[test]
+    fn to_json_then_from_json_round_trips_the_tree() {
+        Python::with_gil(|py| {
+            let mut tree = RadixTree::new();
+            tree.add(py, Some("10.0.0.0/24".to_string()), None, None).unwrap();
+
+            let json = tree.to_json(py).unwrap();
+            let reloaded = RadixTree::from_json(py, &json).unwrap();
+            assert_eq!(reloaded.__len__(), 1);
+            assert!(reloaded
+                .search_exact(py, Some("10.0.0.0/24".to_string()), None, None)
+                .unwrap()
+                .is_some());
+        });
+    }
+}
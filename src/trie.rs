@@ -0,0 +1,451 @@
+//! A binary PATRICIA trie keyed on address bits, giving O(prefix-length)
+//! longest-prefix match instead of the linear scans a flat map requires.
+//!
+//! Every prefix is represented as `(bits: u128, prefix_len: u8)`, top-aligned
+//! so bit `N` is always counted from the MSB of the `u128` regardless of
+//! family (IPv4 keys occupy the top 32 bits, via `Prefix::bits()`). IPv4 and
+//! IPv6 never share a trie; `RadixTree` keeps one root per family.
+//!
+//! Nodes come in two flavors distinguished only by `data`: a "real" node
+//! (`data: Some(..)`) corresponds to a prefix a caller actually inserted; a
+//! "glue" node (`data: None`) exists purely to provide a branch point between
+//! two real prefixes that diverge before either one's `bit_index`. Glue
+//! nodes are created lazily on insert and pruned away on delete once they
+//! stop being needed.
+
+use pyo3::prelude::*;
+use crate::node::RadixNode;
+
+pub struct TrieNode {
+    /// Number of significant leading bits this node's prefix has.
+    bit_index: u8,
+    /// This node's key, with only the leading `bit_index` bits meaningful.
+    bits: u128,
+    data: Option<Py<RadixNode>>,
+    left: Option<Box<TrieNode>>,
+    right: Option<Box<TrieNode>>,
+}
+
+pub enum InsertOutcome {
+    Existing(Py<RadixNode>),
+    Inserted(Py<RadixNode>),
+}
+
+fn mask_to(bits: u128, len: u8) -> u128 {
+    if len == 0 {
+        0
+    } else {
+        bits & (!0u128 << (128 - len as u32))
+    }
+}
+
+/// The bit at position `pos` (0-indexed from the MSB of the `u128`).
+fn test_bit(bits: u128, pos: u8) -> bool {
+    let shift = 127 - pos as u32;
+    (bits >> shift) & 1 == 1
+}
+
+/// How many of the first `limit` bits (from the MSB) of `a` and `b` agree.
+fn common_prefix_len(a: u128, b: u128, limit: u8) -> u8 {
+    let diff = a ^ b;
+    (diff.leading_zeros() as u8).min(limit)
+}
+
+/// Insert `(bits, len)` into the subtree at `slot`, building its `RadixNode`
+/// via `new_node` only if no node already occupies that exact position.
+pub fn insert<F>(
+    slot: &mut Option<Box<TrieNode>>,
+    bits: u128,
+    len: u8,
+    py: Python,
+    new_node: F,
+) -> PyResult<InsertOutcome>
+where
+    F: FnOnce() -> PyResult<Py<RadixNode>>,
+{
+    if slot.is_none() {
+        let py_node = new_node()?;
+        *slot = Some(Box::new(TrieNode {
+            bit_index: len,
+            bits: mask_to(bits, len),
+            data: Some(py_node.clone_ref(py)),
+            left: None,
+            right: None,
+        }));
+        return Ok(InsertOutcome::Inserted(py_node));
+    }
+
+    // Copy out the scalar fields so we're free to `.take()` or re-borrow
+    // `slot` below without the borrow checker treating it as still in use.
+    let node_bit_index = slot.as_ref().unwrap().bit_index;
+    let node_bits = slot.as_ref().unwrap().bits;
+    let limit = node_bit_index.min(len);
+    let common = common_prefix_len(node_bits, bits, limit);
+
+    if common < limit {
+        // The new key diverges from this node before either one's prefix
+        // ends: insert a glue node at the divergence point.
+        let py_node = new_node()?;
+        let new_leaf = Box::new(TrieNode {
+            bit_index: len,
+            bits: mask_to(bits, len),
+            data: Some(py_node.clone_ref(py)),
+            left: None,
+            right: None,
+        });
+        let old_node = slot.take().unwrap();
+        let mut glue = Box::new(TrieNode {
+            bit_index: common,
+            bits: mask_to(bits, common),
+            data: None,
+            left: None,
+            right: None,
+        });
+        if test_bit(bits, common) {
+            glue.right = Some(new_leaf);
+            glue.left = Some(old_node);
+        } else {
+            glue.left = Some(new_leaf);
+            glue.right = Some(old_node);
+        }
+        *slot = Some(glue);
+        return Ok(InsertOutcome::Inserted(py_node));
+    }
+
+    match len.cmp(&node_bit_index) {
+        std::cmp::Ordering::Equal => {
+            let node = slot.as_mut().unwrap();
+            match &node.data {
+                Some(existing) => Ok(InsertOutcome::Existing(existing.clone_ref(py))),
+                None => {
+                    let py_node = new_node()?;
+                    node.data = Some(py_node.clone_ref(py));
+                    Ok(InsertOutcome::Inserted(py_node))
+                }
+            }
+        }
+        std::cmp::Ordering::Less => {
+            // The new prefix is a strict ancestor of this node: splice it in.
+            let py_node = new_node()?;
+            let old_node = slot.take().unwrap();
+            let mut mid = Box::new(TrieNode {
+                bit_index: len,
+                bits: mask_to(bits, len),
+                data: Some(py_node.clone_ref(py)),
+                left: None,
+                right: None,
+            });
+            if test_bit(old_node.bits, len) {
+                mid.right = Some(old_node);
+            } else {
+                mid.left = Some(old_node);
+            }
+            *slot = Some(mid);
+            Ok(InsertOutcome::Inserted(py_node))
+        }
+        std::cmp::Ordering::Greater => {
+            let go_right = test_bit(bits, node_bit_index);
+            let node = slot.as_mut().unwrap();
+            let child = if go_right { &mut node.right } else { &mut node.left };
+            insert(child, bits, len, py, new_node)
+        }
+    }
+}
+
+/// Remove the node exactly at `(bits, len)`, if any, pruning glue nodes left
+/// with no data and at most one child. Returns whether anything was removed.
+pub fn delete(slot: &mut Option<Box<TrieNode>>, bits: u128, len: u8) -> bool {
+    let found = {
+        let node = match slot.as_mut() {
+            Some(node) => node,
+            None => return false,
+        };
+        let limit = node.bit_index.min(len);
+        let common = common_prefix_len(node.bits, bits, limit);
+        if common < limit {
+            false
+        } else {
+            match len.cmp(&node.bit_index) {
+                std::cmp::Ordering::Less => false,
+                std::cmp::Ordering::Equal => {
+                    if node.data.is_some() {
+                        node.data = None;
+                        true
+                    } else {
+                        false
+                    }
+                }
+                std::cmp::Ordering::Greater => {
+                    let go_right = test_bit(bits, node.bit_index);
+                    let child = if go_right { &mut node.right } else { &mut node.left };
+                    delete(child, bits, len)
+                }
+            }
+        }
+    };
+    if found {
+        prune(slot);
+    }
+    found
+}
+
+/// Collapse a node that now carries no data and has at most one child.
+fn prune(slot: &mut Option<Box<TrieNode>>) {
+    let replacement = match slot.take() {
+        None => None,
+        Some(mut node) => {
+            if node.data.is_some() || (node.left.is_some() && node.right.is_some()) {
+                Some(node)
+            } else {
+                node.left.take().or_else(|| node.right.take())
+            }
+        }
+    };
+    *slot = replacement;
+}
+
+/// Find the node stored at exactly `(bits, len)`.
+pub fn search_exact(
+    slot: &Option<Box<TrieNode>>,
+    bits: u128,
+    len: u8,
+    py: Python,
+) -> Option<Py<RadixNode>> {
+    let mut cur = slot;
+    loop {
+        let node = cur.as_ref()?;
+        let limit = node.bit_index.min(len);
+        let common = common_prefix_len(node.bits, bits, limit);
+        if common < limit {
+            return None;
+        }
+        match len.cmp(&node.bit_index) {
+            std::cmp::Ordering::Equal => return node.data.as_ref().map(|d| d.clone_ref(py)),
+            std::cmp::Ordering::Less => return None,
+            std::cmp::Ordering::Greater => {
+                let go_right = test_bit(bits, node.bit_index);
+                cur = if go_right { &node.right } else { &node.left };
+            }
+        }
+    }
+}
+
+/// Walk from the root following `bits`, collecting every real node whose
+/// prefix contains the first `limit` bits of the search key, in descending
+/// (least to most specific) order. Used for both address lookups
+/// (`limit == bit_width`) and prefix-containment lookups (`limit ==
+/// search_prefix.prefix_len`, i.e. `search_covering`).
+pub fn search_along_path(
+    slot: &Option<Box<TrieNode>>,
+    bits: u128,
+    limit: u8,
+    py: Python,
+) -> Vec<Py<RadixNode>> {
+    let mut matches = Vec::new();
+    let mut cur = slot;
+    while let Some(node) = cur {
+        if node.bit_index > limit {
+            break;
+        }
+        let common = common_prefix_len(node.bits, bits, node.bit_index);
+        if common < node.bit_index {
+            break;
+        }
+        if let Some(data) = &node.data {
+            matches.push(data.clone_ref(py));
+        }
+        if node.bit_index == limit {
+            break;
+        }
+        let go_right = test_bit(bits, node.bit_index);
+        cur = if go_right { &node.right } else { &node.left };
+    }
+    matches
+}
+
+/// All real nodes whose prefix is contained within `(bits, len)`.
+pub fn search_covered(
+    slot: &Option<Box<TrieNode>>,
+    bits: u128,
+    len: u8,
+    py: Python,
+) -> Vec<Py<RadixNode>> {
+    let mut cur = slot;
+    loop {
+        let node = match cur {
+            Some(node) => node,
+            None => return Vec::new(),
+        };
+        let limit = node.bit_index.min(len);
+        let common = common_prefix_len(node.bits, bits, limit);
+        if common < limit {
+            return Vec::new();
+        }
+        if node.bit_index >= len {
+            let mut out = Vec::new();
+            collect_subtree(node, py, &mut out);
+            return out;
+        }
+        let go_right = test_bit(bits, node.bit_index);
+        cur = if go_right { &node.right } else { &node.left };
+    }
+}
+
+fn collect_subtree(node: &TrieNode, py: Python, out: &mut Vec<Py<RadixNode>>) {
+    if let Some(data) = &node.data {
+        out.push(data.clone_ref(py));
+    }
+    if let Some(left) = &node.left {
+        collect_subtree(left, py, out);
+    }
+    if let Some(right) = &node.right {
+        collect_subtree(right, py, out);
+    }
+}
+
+/// Every real node in the subtree, in no particular order.
+pub fn collect_all(slot: &Option<Box<TrieNode>>, py: Python, out: &mut Vec<Py<RadixNode>>) {
+    if let Some(node) = slot {
+        collect_subtree(node, py, out);
+    }
+}
+
+pub fn len(slot: &Option<Box<TrieNode>>) -> usize {
+    fn count(node: &TrieNode) -> usize {
+        let mut n = if node.data.is_some() { 1 } else { 0 };
+        if let Some(left) = &node.left {
+            n += count(left);
+        }
+        if let Some(right) = &node.right {
+            n += count(right);
+        }
+        n
+    }
+    slot.as_deref().map_or(0, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prefix::Prefix;
+
+    fn new_node(py: Python, prefix_str: &str) -> PyResult<Py<RadixNode>> {
+        let prefix = Prefix::from_str(prefix_str).unwrap();
+        Py::new(py, RadixNode::new_with_prefix(py, prefix))
+    }
+
+    fn insert_str(slot: &mut Option<Box<TrieNode>>, py: Python, prefix_str: &str) -> InsertOutcome {
+        let prefix = Prefix::from_str(prefix_str).unwrap();
+        insert(slot, prefix.bits(), prefix.prefix_len, py, || new_node(py, prefix_str)).unwrap()
+    }
+
+    #[test]
+    fn insert_into_empty_trie_creates_a_leaf() {
+        Python::with_gil(|py| {
+            let mut root: Option<Box<TrieNode>> = None;
+            let outcome = insert_str(&mut root, py, "10.0.0.0/24");
+            assert!(matches!(outcome, InsertOutcome::Inserted(_)));
+            assert_eq!(len(&root), 1);
+        });
+    }
+
+    #[test]
+    fn inserting_the_same_prefix_twice_returns_existing() {
+        Python::with_gil(|py| {
+            let mut root: Option<Box<TrieNode>> = None;
+            insert_str(&mut root, py, "10.0.0.0/24");
+            let outcome = insert_str(&mut root, py, "10.0.0.0/24");
+            assert!(matches!(outcome, InsertOutcome::Existing(_)));
+            assert_eq!(len(&root), 1);
+        });
+    }
+
+    #[test]
+    fn insert_diverging_prefixes_creates_a_glue_node_but_no_extra_real_node() {
+        Python::with_gil(|py| {
+            let mut root: Option<Box<TrieNode>> = None;
+            insert_str(&mut root, py, "10.0.0.0/25");
+            insert_str(&mut root, py, "10.0.0.128/25");
+            // Both halves are real nodes; their common parent (/24) is a
+            // glue node that shouldn't be counted by len().
+            assert_eq!(len(&root), 2);
+        });
+    }
+
+    #[test]
+    fn search_exact_finds_an_inserted_prefix_and_not_others() {
+        Python::with_gil(|py| {
+            let mut root: Option<Box<TrieNode>> = None;
+            insert_str(&mut root, py, "10.0.0.0/24");
+            let hit = Prefix::from_str("10.0.0.0/24").unwrap();
+            let miss = Prefix::from_str("10.0.1.0/24").unwrap();
+            assert!(search_exact(&root, hit.bits(), hit.prefix_len, py).is_some());
+            assert!(search_exact(&root, miss.bits(), miss.prefix_len, py).is_none());
+        });
+    }
+
+    #[test]
+    fn search_exact_does_not_match_a_covering_prefix() {
+        Python::with_gil(|py| {
+            let mut root: Option<Box<TrieNode>> = None;
+            insert_str(&mut root, py, "10.0.0.0/16");
+            let more_specific = Prefix::from_str("10.0.0.0/24").unwrap();
+            assert!(search_exact(&root, more_specific.bits(), more_specific.prefix_len, py).is_none());
+        });
+    }
+
+    #[test]
+    fn search_along_path_returns_ancestors_least_to_most_specific() {
+        Python::with_gil(|py| {
+            let mut root: Option<Box<TrieNode>> = None;
+            insert_str(&mut root, py, "10.0.0.0/8");
+            insert_str(&mut root, py, "10.0.0.0/16");
+            insert_str(&mut root, py, "10.0.0.0/24");
+            let target = Prefix::from_str("10.0.0.1/32").unwrap();
+            let matches = search_along_path(&root, target.bits(), 32, py);
+            assert_eq!(matches.len(), 3);
+        });
+    }
+
+    #[test]
+    fn search_covered_returns_only_descendants_of_the_given_prefix() {
+        Python::with_gil(|py| {
+            let mut root: Option<Box<TrieNode>> = None;
+            insert_str(&mut root, py, "10.0.0.0/25");
+            insert_str(&mut root, py, "10.0.0.128/25");
+            insert_str(&mut root, py, "192.168.0.0/24");
+            let covering = Prefix::from_str("10.0.0.0/24").unwrap();
+            let matches = search_covered(&root, covering.bits(), covering.prefix_len, py);
+            assert_eq!(matches.len(), 2);
+        });
+    }
+
+    #[test]
+    fn delete_removes_a_leaf_and_prunes_the_resulting_glue_node() {
+        Python::with_gil(|py| {
+            let mut root: Option<Box<TrieNode>> = None;
+            insert_str(&mut root, py, "10.0.0.0/25");
+            insert_str(&mut root, py, "10.0.0.128/25");
+            assert_eq!(len(&root), 2);
+
+            let deleted = Prefix::from_str("10.0.0.128/25").unwrap();
+            assert!(delete(&mut root, deleted.bits(), deleted.prefix_len));
+            assert_eq!(len(&root), 1);
+
+            let remaining = Prefix::from_str("10.0.0.0/25").unwrap();
+            assert!(search_exact(&root, remaining.bits(), remaining.prefix_len, py).is_some());
+            assert!(search_exact(&root, deleted.bits(), deleted.prefix_len, py).is_none());
+        });
+    }
+
+    #[test]
+    fn delete_of_a_prefix_never_inserted_returns_false() {
+        Python::with_gil(|py| {
+            let mut root: Option<Box<TrieNode>> = None;
+            insert_str(&mut root, py, "10.0.0.0/24");
+            let absent = Prefix::from_str("10.0.1.0/24").unwrap();
+            assert!(!delete(&mut root, absent.bits(), absent.prefix_len));
+            assert_eq!(len(&root), 1);
+        });
+    }
+}